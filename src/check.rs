@@ -1,75 +1,303 @@
 use crate::fetch::LicenseReport;
 use anyhow::{bail, Result};
+use std::collections::HashSet;
 
-/// Splits a license expression like "(MIT OR Apache-2.0)" into multiple tokens, e.g. ["MIT", "Apache-2.0"].
-/// We do a naive replacement of " OR ", " AND ", etc. with '|', then split on '|'.
-fn parse_license_expression(license_str: &str) -> Vec<String> {
-    let normalized = license_str
-        .replace(" OR ", "|")
-        .replace(" or ", "|")
-        .replace(" AND ", "|")
-        .replace(" and ", "|");
-
-    normalized
-        .split('|')
-        .map(|s| s.trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')'))
-        .filter(|s| !s.is_empty())
-        .map(String::from)
-        .collect()
+/// A parsed SPDX license expression, as a boolean tree over license identifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// A single SPDX license identifier, e.g. "MIT".
+    License(String),
+    /// `<expr> WITH <exception>`, e.g. "Apache-2.0 WITH LLVM-exception".
+    With(Box<Expr>, String),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// No SPDX expression could be determined (missing or unparsable license metadata).
+    Unknown,
 }
 
-/// Expand each user-supplied `--deny` or `--allow` string the same way, so
-/// `--deny "MIT OR Apache-2.0"` becomes `["MIT", "Apache-2.0"]` in the final list.
-pub fn expand_user_input(license_list: &[String]) -> Vec<String> {
-    let mut expanded = Vec::new();
+/// Tokenize an SPDX expression into identifiers, `AND`/`OR`/`WITH` keywords, and parens.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut cur = String::new();
+    for c in input.chars() {
+        if c == '(' || c == ')' {
+            if !cur.is_empty() {
+                tokens.push(std::mem::take(&mut cur));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_whitespace() {
+            if !cur.is_empty() {
+                tokens.push(std::mem::take(&mut cur));
+            }
+        } else {
+            cur.push(c);
+        }
+    }
+    if !cur.is_empty() {
+        tokens.push(cur);
+    }
+    tokens
+}
+
+/// A small recursive-descent parser for SPDX license expressions.
+///
+/// Precedence (low to high): `OR`, `AND`, `WITH`, parens.
+struct ExprParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+}
+
+impl<'a> ExprParser<'a> {
+    fn new(tokens: &'a [String]) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn bump(&mut self) -> Option<&str> {
+        let tok = self.tokens.get(self.pos).map(String::as_str);
+        self.pos += 1;
+        tok
+    }
+
+    fn eat_keyword(&mut self, kw: &str) -> bool {
+        if matches!(self.peek(), Some(t) if t.eq_ignore_ascii_case(kw)) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_expr(&mut self) -> Expr {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Expr {
+        let mut lhs = self.parse_and();
+        while self.eat_keyword("OR") {
+            let rhs = self.parse_and();
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        lhs
+    }
+
+    fn parse_and(&mut self) -> Expr {
+        let mut lhs = self.parse_with();
+        while self.eat_keyword("AND") {
+            let rhs = self.parse_with();
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        lhs
+    }
+
+    fn parse_with(&mut self) -> Expr {
+        let atom = self.parse_atom();
+        if self.eat_keyword("WITH") {
+            if let Some(exception) = self.bump() {
+                return Expr::With(Box::new(atom), exception.to_string());
+            }
+        }
+        atom
+    }
+
+    fn parse_atom(&mut self) -> Expr {
+        match self.bump() {
+            Some("(") => {
+                let inner = self.parse_expr();
+                if matches!(self.peek(), Some(")")) {
+                    self.bump();
+                }
+                inner
+            }
+            Some(id) => Expr::License(id.to_string()),
+            None => Expr::Unknown,
+        }
+    }
+}
+
+/// Parse a crate's reported license string into an SPDX expression tree.
+///
+/// The `No license listed` and `Failed: ...` sentinels produced by
+/// [`crate::fetch::build_license_report`] become [`Expr::Unknown`].
+pub fn parse_license_expression(license_str: &str) -> Expr {
+    if license_str == "No license listed" || license_str.starts_with("Failed:") {
+        return Expr::Unknown;
+    }
+
+    let tokens = tokenize(license_str);
+    if tokens.is_empty() {
+        return Expr::Unknown;
+    }
+
+    ExprParser::new(&tokens).parse_expr()
+}
+
+/// Is `expr` satisfiable using only licenses in `allow`?
+///
+/// A `License` leaf (and the base license of a `WITH` exception) must be in
+/// `allow`; `And` requires both sides to be allowed, `Or` either side.
+/// `Unknown` never satisfies a non-empty allow-list.
+fn allowed_by(expr: &Expr, allow: &HashSet<String>) -> bool {
+    match expr {
+        Expr::License(id) => allow.contains(id),
+        Expr::With(base, _exception) => allowed_by(base, allow),
+        Expr::And(a, b) => allowed_by(a, allow) && allowed_by(b, allow),
+        Expr::Or(a, b) => allowed_by(a, allow) || allowed_by(b, allow),
+        Expr::Unknown => false,
+    }
+}
+
+/// Does every satisfying assignment of `expr` necessarily include a denied license?
+///
+/// Evaluates `expr` treating each denied license as `false` and everything
+/// else as `true`; the crate is a violation iff the expression evaluates to
+/// `false`, i.e. there is no way to satisfy it without a denied license.
+fn denied_by(expr: &Expr, deny: &HashSet<String>) -> bool {
+    match expr {
+        Expr::License(id) => !deny.contains(id),
+        Expr::With(base, _exception) => denied_by(base, deny),
+        Expr::And(a, b) => denied_by(a, deny) && denied_by(b, deny),
+        Expr::Or(a, b) => denied_by(a, deny) || denied_by(b, deny),
+        Expr::Unknown => true,
+    }
+}
+
+/// Expand each user-supplied `--deny` or `--allow` string into the individual
+/// license identifiers it names, so `--allow "MIT OR Apache-2.0"` is treated
+/// as allowing either one.
+pub fn expand_user_input(license_list: &[String]) -> HashSet<String> {
+    let mut expanded = HashSet::new();
     for item in license_list {
-        let subparts = parse_license_expression(item);
-        expanded.extend(subparts);
+        collect_identifiers(&parse_license_expression(item), &mut expanded);
     }
     expanded
 }
 
-/// Checks each crate's license(s) against the expanded deny/allow lists.
-/// - If ANY sub-license is in `deny`, that's a violation.
-/// - If `allow` is non-empty, ALL sub-licenses must be in `allow` or it's a violation.
+fn collect_identifiers(expr: &Expr, out: &mut HashSet<String>) {
+    match expr {
+        Expr::License(id) => {
+            out.insert(id.clone());
+        }
+        Expr::With(base, _exception) => collect_identifiers(base, out),
+        Expr::And(a, b) | Expr::Or(a, b) => {
+            collect_identifiers(a, out);
+            collect_identifiers(b, out);
+        }
+        Expr::Unknown => {}
+    }
+}
+
+/// Checks each crate's SPDX license expression against the deny/allow lists.
+///
+/// - If `deny` is non-empty and a crate's expression necessarily pulls in a
+///   denied license, that's a violation.
+/// - If `allow` is non-empty and a crate's expression can't be satisfied
+///   using only allowed licenses, that's a violation.
 ///
 /// We accumulate *all* violations, then fail at the end if any are found.
 pub fn check_licenses(reports: &[LicenseReport], deny: &[String], allow: &[String]) -> Result<()> {
+    let deny_set = expand_user_input(deny);
+    let allow_set = expand_user_input(allow);
+
     let mut violations = Vec::new();
 
     for r in reports {
-        let sub_licenses = parse_license_expression(&r.license);
-
-        // DENY: if any sub-license is in deny => violation
-        if !deny.is_empty() {
-            for lic in &sub_licenses {
-                if deny.contains(lic) {
-                    violations.push(format!(
-                        "Crate '{}': sub-license '{}' is in the deny list.",
-                        r.crate_name, lic
-                    ));
-                }
-            }
+        let expr = parse_license_expression(&r.license);
+
+        if !deny_set.is_empty() && !denied_by(&expr, &deny_set) {
+            violations.push(format!(
+                "Crate '{}': license '{}' necessarily includes a denied license.",
+                r.crate_name, r.license
+            ));
         }
 
-        // ALLOW: if not empty, all sub-licenses must appear
-        if !allow.is_empty() {
-            for lic in &sub_licenses {
-                if !allow.contains(lic) {
-                    violations.push(format!(
-                        "Crate '{}': sub-license '{}' is NOT in the allow list.",
-                        r.crate_name, lic
-                    ));
-                }
-            }
+        if !allow_set.is_empty() && !allowed_by(&expr, &allow_set) {
+            violations.push(format!(
+                "Crate '{}': license '{}' cannot be satisfied by the allow list.",
+                r.crate_name, r.license
+            ));
         }
     }
 
     if !violations.is_empty() {
         let msg = violations.join("\n");
-        // Combine them in a single error
         bail!("License check found these violations:\n{}", msg);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(license: &str) -> LicenseReport {
+        LicenseReport {
+            crate_name: "some-crate".to_string(),
+            matched_version: "1.0.0".to_string(),
+            license: license.to_string(),
+            confidence: None,
+            target: None,
+        }
+    }
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn or_is_satisfied_by_either_side() {
+        let expr = parse_license_expression("MIT OR GPL-3.0");
+        assert!(allowed_by(&expr, &set(&["MIT"])));
+        assert!(!denied_by(&expr, &set(&["MIT"])));
+        assert!(denied_by(&expr, &set(&["GPL-3.0"])));
+    }
+
+    #[test]
+    fn and_requires_both_sides() {
+        let expr = parse_license_expression("MIT AND GPL-3.0");
+        assert!(!allowed_by(&expr, &set(&["MIT"])));
+        assert!(allowed_by(&expr, &set(&["MIT", "GPL-3.0"])));
+        assert!(!denied_by(&expr, &set(&["GPL-3.0"])));
+    }
+
+    #[test]
+    fn with_exception_checks_the_base_license() {
+        let expr = parse_license_expression("Apache-2.0 WITH LLVM-exception");
+        assert!(allowed_by(&expr, &set(&["Apache-2.0"])));
+        assert!(!denied_by(&expr, &set(&["Apache-2.0"])));
+    }
+
+    #[test]
+    fn parens_override_default_precedence() {
+        let expr = parse_license_expression("(MIT OR Apache-2.0) AND GPL-3.0");
+        assert!(denied_by(&expr, &set(&["MIT", "Apache-2.0"])));
+        assert!(!denied_by(&expr, &set(&["GPL-3.0"])));
+    }
+
+    #[test]
+    fn unknown_fails_a_non_empty_allow_list_but_never_denies() {
+        assert_eq!(parse_license_expression("No license listed"), Expr::Unknown);
+        assert_eq!(parse_license_expression("Failed: timed out"), Expr::Unknown);
+
+        let expr = Expr::Unknown;
+        assert!(!allowed_by(&expr, &set(&["MIT"])));
+        assert!(denied_by(&expr, &set(&["GPL-3.0"])));
+    }
+
+    #[test]
+    fn dual_license_passes_when_one_side_is_allowed() {
+        let reports = [report("MIT OR GPL-3.0")];
+        assert!(check_licenses(&reports, &[], &["MIT".to_string()]).is_ok());
+        assert!(check_licenses(&reports, &["GPL-3.0".to_string()], &[]).is_ok());
+    }
+
+    #[test]
+    fn conjunctive_license_fails_when_one_side_is_missing_from_allow_list() {
+        let reports = [report("MIT AND GPL-3.0")];
+        assert!(check_licenses(&reports, &[], &["MIT".to_string()]).is_err());
+    }
+}
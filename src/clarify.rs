@@ -0,0 +1,112 @@
+//! User-supplied license clarifications/overrides, read from `licenses.toml`.
+//!
+//! Some crates report no SPDX license, a non-SPDX string, or metadata that's
+//! simply wrong. A `[[clarify]]` entry gives an authoritative license for a
+//! crate (optionally scoped to a semver range), overriding whatever
+//! crates.io reported.
+
+use crate::scan;
+use anyhow::{Context, Result};
+use reqwest::Client;
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct ClarifyFile {
+    #[serde(default)]
+    clarify: Vec<Clarification>,
+}
+
+/// A single `[[clarify]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Clarification {
+    pub name: String,
+    /// Semver range this clarification applies to; absent means "all versions".
+    #[serde(default)]
+    pub version: Option<String>,
+    pub license: String,
+    /// File expected to contain the license text, guarded by `expected_hash`.
+    #[serde(default)]
+    pub expected_file: Option<String>,
+    /// Hex-encoded SHA-256 of `expected_file`'s contents.
+    #[serde(default)]
+    pub expected_hash: Option<String>,
+}
+
+/// All clarifications loaded from a `licenses.toml`.
+#[derive(Debug, Default, Clone)]
+pub struct Clarifications(Vec<Clarification>);
+
+impl Clarifications {
+    /// Load clarifications from `path`; a missing file just means none apply.
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to open {path}"))?;
+        let parsed: ClarifyFile =
+            toml::from_str(&contents).with_context(|| format!("Failed to parse {path}"))?;
+        Ok(Self(parsed.clarify))
+    }
+
+    /// Find the clarification, if any, that applies to `name`@`version`.
+    pub fn find(&self, name: &str, version: &str) -> Option<&Clarification> {
+        self.0.iter().find(|c| {
+            c.name == name
+                && c.version.as_deref().is_none_or(|req| {
+                    VersionReq::parse(req)
+                        .ok()
+                        .zip(Version::parse(version).ok())
+                        .is_some_and(|(req, v)| req.matches(&v))
+                })
+        })
+    }
+}
+
+/// Apply `clarification` to `name`@`version`, returning its authoritative
+/// license plus a warning message if the clarification is hash-guarded and
+/// the crate's shipped license file no longer matches.
+pub async fn apply(
+    client: &Client,
+    name: &str,
+    version: &str,
+    clarification: &Clarification,
+) -> (String, Option<String>) {
+    let warning = match (&clarification.expected_file, &clarification.expected_hash) {
+        (Some(file), Some(hash)) => verify_hash(client, name, version, file, hash)
+            .await
+            .err()
+            .map(|e| format!("clarify '{name}': {e}")),
+        _ => None,
+    };
+
+    (clarification.license.clone(), warning)
+}
+
+async fn verify_hash(
+    client: &Client,
+    name: &str,
+    version: &str,
+    expected_file: &str,
+    expected_hash: &str,
+) -> Result<()> {
+    let bytes = scan::read_crate_file(client, name, version, expected_file)
+        .await?
+        .with_context(|| format!("expected file '{expected_file}' not found in {name} {version}"))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    let actual_hash = format!("{:x}", hasher.finalize());
+
+    if actual_hash != expected_hash.to_ascii_lowercase() {
+        anyhow::bail!(
+            "'{expected_file}' hash changed (expected {expected_hash}, got {actual_hash})"
+        );
+    }
+
+    Ok(())
+}
@@ -26,6 +26,21 @@ pub enum Command {
 
         #[arg(long)]
         skip_optional: bool,
+
+        /// Resolve the full transitive dependency graph from Cargo.lock
+        /// instead of only the direct dependencies in Cargo.toml
+        #[arg(long, default_value_t = false)]
+        all: bool,
+
+        /// Fall back to scanning a crate's shipped license files when
+        /// crates.io has no license metadata for it
+        #[arg(long, default_value_t = false)]
+        scan_files: bool,
+
+        /// Only report dependencies active for this cfg expression or
+        /// target triple (e.g. `cfg(windows)`, `wasm32-unknown-unknown`)
+        #[arg(long)]
+        target: Option<String>,
     },
 
     /// Just list direct dependencies
@@ -36,6 +51,11 @@ pub enum Command {
         build: bool,
         #[arg(long, default_value_t = false)]
         skip_optional: bool,
+
+        /// Only list dependencies active for this cfg expression or target
+        /// triple (e.g. `cfg(windows)`, `wasm32-unknown-unknown`)
+        #[arg(long)]
+        target: Option<String>,
     },
 
     /// Check licenses against a deny/allow list
@@ -54,6 +74,21 @@ pub enum Command {
         build: bool,
         #[arg(long)]
         skip_optional: bool,
+
+        /// Resolve the full transitive dependency graph from Cargo.lock
+        /// instead of only the direct dependencies in Cargo.toml
+        #[arg(long, default_value_t = false)]
+        all: bool,
+
+        /// Fall back to scanning a crate's shipped license files when
+        /// crates.io has no license metadata for it
+        #[arg(long, default_value_t = false)]
+        scan_files: bool,
+
+        /// Only check dependencies active for this cfg expression or target
+        /// triple (e.g. `cfg(windows)`, `wasm32-unknown-unknown`)
+        #[arg(long)]
+        target: Option<String>,
     },
 
     Version,
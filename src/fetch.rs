@@ -5,7 +5,9 @@ use reqwest::Client;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
+use crate::clarify::Clarifications;
 use crate::parse::Dep;
+use crate::scan;
 
 /// Data from crates.io: GET /crates/&lt;crate&gt;/versions
 #[derive(Debug, Deserialize)]
@@ -24,24 +26,80 @@ pub struct LicenseReport {
     pub crate_name: String,
     pub matched_version: String,
     pub license: String,
+    /// Confidence score from scanning the crate's shipped license file,
+    /// present only when crates.io had no license metadata and
+    /// `--scan-files` found a match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    /// The `[target]` expression this dependency is gated on, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
 }
 
-pub async fn build_license_report(deps: &[Dep], client: &Client) -> Result<Vec<LicenseReport>> {
+/// Fetch license metadata for every dep from crates.io, falling back to
+/// scanning each crate's shipped license files (via [`scan`]) when
+/// `scan_files` is set and crates.io reports none, then applying any
+/// matching override from `clarifications`.
+pub async fn build_license_report(
+    deps: &[Dep],
+    client: &Client,
+    scan_files: bool,
+    clarifications: &Clarifications,
+) -> Result<Vec<LicenseReport>> {
     let mut futures = FuturesUnordered::new();
 
     for dep in deps.iter().cloned() {
         let c = client.clone();
+        let target = dep.target.clone();
+        let clarifications = clarifications.clone();
         futures.push(async move {
             match fetch_best_match(&c, &dep.name, &dep.version_req).await {
-                Ok((matched_ver, license)) => LicenseReport {
-                    crate_name: dep.name,
-                    matched_version: matched_ver,
-                    license: license.unwrap_or_else(|| "No license listed".to_string()),
-                },
+                Ok((matched_ver, license)) => {
+                    let (license, confidence) = match license {
+                        Some(license) => (license, None),
+                        None if scan_files => {
+                            match scan::scan_crate_license(&c, &dep.name, &matched_ver).await {
+                                Ok(Some(s)) => (s.expression, Some(s.confidence)),
+                                Ok(None) => ("No license listed".to_string(), None),
+                                // A scan failure (missing SPDX cache, a failed download, a
+                                // corrupt archive, ...) is not the same as "we scanned and
+                                // found nothing" — surface it like the crates.io fetch
+                                // failure above instead of silently reporting no license.
+                                Err(e) => (format!("Failed: {e}"), None),
+                            }
+                        }
+                        None => ("No license listed".to_string(), None),
+                    };
+
+                    let (license, confidence) = match clarifications.find(&dep.name, &matched_ver) {
+                        Some(clarification) => {
+                            let (clarified, warning) =
+                                crate::clarify::apply(&c, &dep.name, &matched_ver, clarification)
+                                    .await;
+                            if let Some(warning) = warning {
+                                eprintln!("warning: {warning}");
+                            }
+                            // The clarification replaces the license wholesale, so any
+                            // confidence score from a preceding file scan no longer applies.
+                            (clarified, None)
+                        }
+                        None => (license, confidence),
+                    };
+
+                    LicenseReport {
+                        crate_name: dep.name,
+                        matched_version: matched_ver,
+                        license,
+                        confidence,
+                        target,
+                    }
+                }
                 Err(e) => LicenseReport {
                     crate_name: dep.name,
                     matched_version: "unknown".into(),
                     license: format!("Failed: {e}"),
+                    confidence: None,
+                    target,
                 },
             }
         });
@@ -1,61 +1,201 @@
-use anyhow::{Context, Result};
-use std::collections::HashSet;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
 use std::io::Read;
+use std::path::{Path, PathBuf};
 use toml::Value;
 
 #[derive(Debug, Clone)]
 pub struct Dep {
     pub name: String,
     pub version_req: String,
+    /// The `[target.'cfg(...)']`/`[target.<triple>]` expression that pulled
+    /// this dependency in, or `None` for an unconditional dependency.
+    pub target: Option<String>,
 }
 
+/// Parse dependencies from `path`, following `[workspace]` members and
+/// resolving `dep = { workspace = true }` inheritance if present.
 pub fn parse_cargo_toml(
     path: &str,
     include_dev: bool,
     include_build: bool,
     skip_optional: bool,
 ) -> Result<Vec<Dep>> {
-    let mut file = File::open(path).context("Failed to open Cargo.toml")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let toml_val: Value = toml::from_str(&contents)?;
+    let toml_val = read_manifest(path)?;
 
-    let mut all_deps = Vec::new();
+    let workspace = toml_val.get("workspace").and_then(Value::as_table);
+    let workspace_deps = workspace
+        .and_then(|w| w.get("dependencies"))
+        .and_then(Value::as_table);
 
-    if let Some(table) = toml_val.get("dependencies").and_then(|v| v.as_table()) {
-        all_deps.extend(parse_deps_table(table, skip_optional)?);
-    }
-    if include_dev {
-        if let Some(table) = toml_val.get("dev-dependencies").and_then(|v| v.as_table()) {
-            all_deps.extend(parse_deps_table(table, skip_optional)?);
+    let mut all_deps =
+        collect_manifest_deps(&toml_val, include_dev, include_build, skip_optional, workspace_deps)?;
+
+    if let Some(workspace) = workspace {
+        let root_dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+        for member_manifest in expand_workspace_members(workspace, root_dir)? {
+            let member_val = read_manifest(
+                member_manifest
+                    .to_str()
+                    .context("Workspace member path is not valid UTF-8")?,
+            )?;
+            all_deps.extend(collect_manifest_deps(
+                &member_val,
+                include_dev,
+                include_build,
+                skip_optional,
+                workspace_deps,
+            )?);
         }
     }
-    if include_build {
-        if let Some(table) = toml_val
-            .get("build-dependencies")
-            .and_then(|v| v.as_table())
-        {
-            all_deps.extend(parse_deps_table(table, skip_optional)?);
-        }
+
+    Ok(dedupe(all_deps))
+}
+
+/// Keep only unconditional dependencies plus those gated on `target`.
+pub fn filter_by_target(deps: Vec<Dep>, target: Option<&str>) -> Vec<Dep> {
+    match target {
+        None => deps,
+        Some(target) => deps
+            .into_iter()
+            .filter(|d| d.target.is_none() || d.target.as_deref() == Some(target))
+            .collect(),
     }
+}
 
+fn read_manifest(path: &str) -> Result<Value> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {path}"))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    toml::from_str(&contents).with_context(|| format!("Failed to parse {path}"))
+}
+
+fn dedupe(deps: Vec<Dep>) -> Vec<Dep> {
     let mut seen = HashSet::new();
     let mut unique = Vec::new();
-    for d in all_deps {
-        let key = (d.name.clone(), d.version_req.clone());
+    for d in deps {
+        let key = (d.name.clone(), d.version_req.clone(), d.target.clone());
         if !seen.contains(&key) {
             seen.insert(key);
             unique.push(d);
         }
     }
+    unique
+}
+
+/// Expand a `[workspace]` table's `members` glob patterns (honoring
+/// `exclude`) into the `Cargo.toml` path of each member.
+fn expand_workspace_members(
+    workspace: &toml::map::Map<String, Value>,
+    root_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let members = workspace
+        .get("members")
+        .and_then(Value::as_array)
+        .map(|arr| arr.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
 
-    Ok(unique)
+    let exclude: HashSet<&str> = workspace
+        .get("exclude")
+        .and_then(Value::as_array)
+        .map(|arr| arr.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut manifests = Vec::new();
+    for pattern in members {
+        let glob_pattern = root_dir.join(pattern);
+        let pattern_str = glob_pattern.to_string_lossy();
+        for entry in glob::glob(&pattern_str)
+            .with_context(|| format!("Invalid workspace member glob '{pattern}'"))?
+        {
+            let member_dir = entry
+                .with_context(|| format!("Failed to expand workspace member glob '{pattern}'"))?;
+            if exclude
+                .iter()
+                .any(|ex| member_dir.ends_with(Path::new(ex)))
+            {
+                continue;
+            }
+            let manifest_path = member_dir.join("Cargo.toml");
+            if manifest_path.exists() {
+                manifests.push(manifest_path);
+            }
+        }
+    }
+    Ok(manifests)
+}
+
+/// Parse `[dependencies]` (and optionally `[dev-dependencies]` /
+/// `[build-dependencies]`) out of a single manifest's parsed TOML value,
+/// including every platform-gated table under `[target]`.
+fn collect_manifest_deps(
+    manifest: &Value,
+    include_dev: bool,
+    include_build: bool,
+    skip_optional: bool,
+    workspace_deps: Option<&toml::map::Map<String, Value>>,
+) -> Result<Vec<Dep>> {
+    let mut deps = collect_deps_tables(
+        manifest,
+        include_dev,
+        include_build,
+        skip_optional,
+        workspace_deps,
+        None,
+    )?;
+
+    if let Some(target_table) = manifest.get("target").and_then(Value::as_table) {
+        for (target_expr, target_manifest) in target_table {
+            deps.extend(collect_deps_tables(
+                target_manifest,
+                include_dev,
+                include_build,
+                skip_optional,
+                workspace_deps,
+                Some(target_expr),
+            )?);
+        }
+    }
+
+    Ok(deps)
+}
+
+/// Parse `[dependencies]`, and optionally `[dev-dependencies]` /
+/// `[build-dependencies]`, out of a single (possibly target-scoped) table.
+fn collect_deps_tables(
+    manifest: &Value,
+    include_dev: bool,
+    include_build: bool,
+    skip_optional: bool,
+    workspace_deps: Option<&toml::map::Map<String, Value>>,
+    target: Option<&str>,
+) -> Result<Vec<Dep>> {
+    let mut deps = Vec::new();
+
+    if let Some(table) = manifest.get("dependencies").and_then(Value::as_table) {
+        deps.extend(parse_deps_table(table, skip_optional, workspace_deps, target)?);
+    }
+    if include_dev {
+        if let Some(table) = manifest.get("dev-dependencies").and_then(Value::as_table) {
+            deps.extend(parse_deps_table(table, skip_optional, workspace_deps, target)?);
+        }
+    }
+    if include_build {
+        if let Some(table) = manifest.get("build-dependencies").and_then(Value::as_table) {
+            deps.extend(parse_deps_table(table, skip_optional, workspace_deps, target)?);
+        }
+    }
+
+    Ok(deps)
 }
 
 fn parse_deps_table(
     table: &toml::map::Map<String, Value>,
     skip_optional: bool,
+    workspace_deps: Option<&toml::map::Map<String, Value>>,
+    target: Option<&str>,
 ) -> Result<Vec<Dep>> {
     let mut deps = Vec::new();
 
@@ -65,9 +205,27 @@ fn parse_deps_table(
                 deps.push(Dep {
                     name: name.clone(),
                     version_req: ver_req.clone(),
+                    target: target.map(String::from),
                 });
             }
             Value::Table(tbl) => {
+                if let Some(Value::Boolean(true)) = tbl.get("workspace") {
+                    let optional = matches!(tbl.get("optional"), Some(Value::Boolean(true)));
+                    if skip_optional && optional {
+                        continue;
+                    }
+                    let version_req = workspace_deps
+                        .and_then(|ws| ws.get(name))
+                        .map(workspace_dep_version_req)
+                        .unwrap_or_else(|| "unspecified".to_string());
+                    deps.push(Dep {
+                        name: name.clone(),
+                        version_req,
+                        target: target.map(String::from),
+                    });
+                    continue;
+                }
+
                 if skip_optional {
                     if let Some(Value::Boolean(true)) = tbl.get("optional") {
                         continue;
@@ -81,12 +239,14 @@ fn parse_deps_table(
                 deps.push(Dep {
                     name: name.clone(),
                     version_req,
+                    target: target.map(String::from),
                 });
             }
             _ => {
                 deps.push(Dep {
                     name: name.clone(),
                     version_req: "unspecified".to_string(),
+                    target: target.map(String::from),
                 });
             }
         }
@@ -94,3 +254,147 @@ fn parse_deps_table(
 
     Ok(deps)
 }
+
+/// Extract the version requirement from a `[workspace.dependencies]` entry,
+/// which may be a bare string or a table with a `version` key.
+fn workspace_dep_version_req(value: &Value) -> String {
+    match value {
+        Value::String(v) => v.clone(),
+        Value::Table(tbl) => match tbl.get("version") {
+            Some(Value::String(v)) => v.clone(),
+            _ => "unspecified".to_string(),
+        },
+        _ => "unspecified".to_string(),
+    }
+}
+
+/// A single `[[package]]` entry from Cargo.lock.
+#[derive(Debug, Deserialize)]
+struct LockPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockPackage>,
+}
+
+/// Resolve the full transitive dependency graph from `Cargo.lock`, starting
+/// at the root package(s) named in `manifest_path`.
+///
+/// If `manifest_path` has a `[package]` table, it alone is the root. If it's
+/// a virtual workspace manifest (`[workspace]` with no `[package]`), every
+/// member's package is a root, matching how Cargo itself resolves the whole
+/// workspace.
+///
+/// Each entry in a lock package's `dependencies` array is `"name"`, `"name
+/// version"`, or `"name version (source)"`; we only ever need the name and,
+/// when present, the version to disambiguate crates with multiple resolved
+/// versions in the graph. The returned `Vec<Dep>` carries exact `=<version>`
+/// constraints and excludes the root package(s) themselves.
+pub fn parse_cargo_lock(lock_path: &str, manifest_path: &str) -> Result<Vec<Dep>> {
+    let lock_contents =
+        std::fs::read_to_string(lock_path).with_context(|| format!("Failed to open {lock_path}"))?;
+    let lock: CargoLock =
+        toml::from_str(&lock_contents).with_context(|| format!("Failed to parse {lock_path}"))?;
+
+    let manifest = read_manifest(manifest_path)?;
+    let root_names = root_package_names(&manifest, manifest_path)?;
+
+    let mut index: HashMap<&str, Vec<&LockPackage>> = HashMap::new();
+    for pkg in &lock.package {
+        index.entry(pkg.name.as_str()).or_default().push(pkg);
+    }
+
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    for root_name in &root_names {
+        let root = index
+            .get(root_name.as_str())
+            .and_then(|candidates| candidates.first().copied())
+            .with_context(|| format!("Root crate '{root_name}' not found in {lock_path}"))?;
+        // Roots are never reported themselves, even if another root depends on them.
+        visited.insert((root.name.clone(), root.version.clone()));
+        queue.push_back(root);
+    }
+
+    let mut deps = Vec::new();
+    while let Some(pkg) = queue.pop_front() {
+        for dep_str in &pkg.dependencies {
+            if let Some(child) = resolve_lock_dependency(&index, dep_str) {
+                let key = (child.name.clone(), child.version.clone());
+                if visited.insert(key) {
+                    deps.push(Dep {
+                        name: child.name.clone(),
+                        version_req: format!("={}", child.version),
+                        target: None,
+                    });
+                    queue.push_back(child);
+                }
+            }
+        }
+    }
+
+    Ok(deps)
+}
+
+/// The package name(s) that seed the Cargo.lock BFS: the manifest's own
+/// `[package].name` if it has one, or every `[workspace]` member's package
+/// name for a virtual workspace root.
+fn root_package_names(manifest: &Value, manifest_path: &str) -> Result<Vec<String>> {
+    if let Some(name) = manifest
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(Value::as_str)
+    {
+        return Ok(vec![name.to_string()]);
+    }
+
+    let workspace = manifest.get("workspace").and_then(Value::as_table).with_context(|| {
+        format!("{manifest_path} has no [package] or [workspace] table; cannot determine the root crate(s) to resolve from")
+    })?;
+
+    let root_dir = Path::new(manifest_path).parent().unwrap_or_else(|| Path::new("."));
+    let mut names = Vec::new();
+    for member_manifest in expand_workspace_members(workspace, root_dir)? {
+        let member_path = member_manifest
+            .to_str()
+            .context("Workspace member path is not valid UTF-8")?;
+        let member_val = read_manifest(member_path)?;
+        if let Some(name) = member_val
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(Value::as_str)
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    if names.is_empty() {
+        bail!("{manifest_path}'s [workspace] has no members with a [package] table");
+    }
+
+    Ok(names)
+}
+
+/// Match a Cargo.lock dependency string against the package index, using the
+/// embedded version to disambiguate when a crate has multiple resolved
+/// versions in the graph.
+fn resolve_lock_dependency<'a>(
+    index: &HashMap<&str, Vec<&'a LockPackage>>,
+    dep_str: &str,
+) -> Option<&'a LockPackage> {
+    let mut parts = dep_str.split_whitespace();
+    let name = parts.next()?;
+    let version = parts.next();
+    let candidates = index.get(name)?;
+
+    match version {
+        Some(v) => candidates.iter().find(|p| p.version == v).copied(),
+        None => candidates.first().copied(),
+    }
+}
@@ -16,11 +16,28 @@ pub fn write_markdown(results: &[LicenseReport]) -> Result<()> {
     )?;
 
     for r in results {
-        writeln!(
-            file,
-            "- **{}** (version: `{}`) → *{}*",
-            r.crate_name, r.matched_version, r.license
-        )?;
+        let target_suffix = r
+            .target
+            .as_ref()
+            .map(|t| format!(" (target: `{t}`)"))
+            .unwrap_or_default();
+
+        match r.confidence {
+            Some(confidence) => writeln!(
+                file,
+                "- **{}** (version: `{}`){} → *{}* (scanned, confidence {:.0}%)",
+                r.crate_name,
+                r.matched_version,
+                target_suffix,
+                r.license,
+                confidence * 100.0
+            )?,
+            None => writeln!(
+                file,
+                "- **{}** (version: `{}`){} → *{}*",
+                r.crate_name, r.matched_version, target_suffix, r.license
+            )?,
+        }
     }
 
     println!("Generated Markdown: {path}");
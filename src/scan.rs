@@ -0,0 +1,151 @@
+//! Fallback license detection by scanning a crate's actual shipped files.
+//!
+//! Used when crates.io has no `license` metadata for a version: we download
+//! the crate's tarball, look for files that are conventionally license
+//! texts, and identify each one's SPDX license by fuzzy-matching it against
+//! askalono's embedded SPDX text cache.
+
+use anyhow::{anyhow, bail, Context, Result};
+use askalono::Store;
+use flate2::read::GzDecoder;
+use reqwest::Client;
+use std::io::Read as _;
+use tar::Archive;
+
+/// Minimum askalono match score to trust a scanned file as the license.
+const CONFIDENCE_THRESHOLD: f32 = 0.8;
+
+/// Path to the built askalono SPDX cache (produced by `askalono cache build`
+/// from the SPDX license-list-data). Read at runtime, not embedded, so a
+/// missing cache is a clean "can't scan" error rather than a build failure.
+/// Not vendored in this repo; see `license-cache/README.md` for how to build
+/// one. Its absence is a fatal error for this scan (propagated by `?` below),
+/// not a silent "no license found".
+const SPDX_CACHE_PATH: &str = "license-cache/cache.bin.zstd";
+
+/// File stems (before any extension) that are conventionally license texts.
+const LICENSE_FILE_STEMS: &[&str] = &[
+    "LICENSE",
+    "LICENSE-MIT",
+    "LICENSE-APACHE",
+    "COPYING",
+    "UNLICENSE",
+];
+
+/// An SPDX license identified by scanning a crate's shipped license file.
+#[derive(Debug, Clone)]
+pub struct ScannedLicense {
+    pub expression: String,
+    pub confidence: f32,
+}
+
+/// Download `<name>-<version>.crate` from static.crates.io and scan it for
+/// license files, returning the highest-confidence SPDX match above
+/// [`CONFIDENCE_THRESHOLD`], if any.
+///
+/// Requires [`SPDX_CACHE_PATH`] (built with `askalono cache build` from the
+/// SPDX license-list-data) to exist alongside the binary, since askalono
+/// ships no cache of its own.
+pub async fn scan_crate_license(
+    client: &Client,
+    name: &str,
+    version: &str,
+) -> Result<Option<ScannedLicense>> {
+    let bytes = download_crate_tarball(client, name, version).await?;
+
+    let cache_bytes = std::fs::read(SPDX_CACHE_PATH).with_context(|| {
+        format!("SPDX license cache not found at {SPDX_CACHE_PATH} (run `askalono cache build` to create it)")
+    })?;
+    // askalono's error type doesn't implement `std::error::Error`, so it
+    // can't flow through `.context()`; fold it into an anyhow error by hand.
+    let store = Store::from_cache(&cache_bytes[..])
+        .map_err(|e| anyhow!("Failed to load SPDX license cache from {SPDX_CACHE_PATH}: {e}"))?;
+
+    let decoder = GzDecoder::new(&bytes[..]);
+    let mut archive = Archive::new(decoder);
+
+    let mut best: Option<ScannedLicense> = None;
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Failed to read tarball entries for {name} {version}"))?
+    {
+        let mut entry = entry.with_context(|| format!("Failed to read a tarball entry for {name} {version}"))?;
+        let path = entry.path()?.into_owned();
+        let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+        if !looks_like_license_file(file_name) {
+            continue;
+        }
+
+        let mut text = String::new();
+        if entry.read_to_string(&mut text).is_err() {
+            continue;
+        }
+
+        let matched = store.analyze(&text.into());
+        if matched.score >= CONFIDENCE_THRESHOLD
+            && best.as_ref().is_none_or(|b| matched.score > b.confidence)
+        {
+            best = Some(ScannedLicense {
+                expression: matched.name.to_string(),
+                confidence: matched.score,
+            });
+        }
+    }
+
+    Ok(best)
+}
+
+/// Does `file_name` look like a license file, case-insensitively and
+/// ignoring any extension (`LICENSE`, `LICENSE-MIT`, `LICENSE.md`, ...)?
+fn looks_like_license_file(file_name: &str) -> bool {
+    let upper = file_name.to_ascii_uppercase();
+    let stem = upper.split('.').next().unwrap_or(&upper);
+    LICENSE_FILE_STEMS.contains(&stem)
+}
+
+/// Download `<name>-<version>.crate` from static.crates.io as raw bytes.
+async fn download_crate_tarball(client: &Client, name: &str, version: &str) -> Result<bytes::Bytes> {
+    let url = format!("https://static.crates.io/crates/{name}/{name}-{version}.crate");
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {url}"))?;
+
+    if !resp.status().is_success() {
+        bail!("static.crates.io returned status {} for {url}", resp.status());
+    }
+
+    resp.bytes()
+        .await
+        .with_context(|| format!("Failed to read tarball body for {name} {version}"))
+}
+
+/// Download `<name>-<version>.crate` and return the raw bytes of `file_name`
+/// inside it, if the tarball contains a file with that name (crate tarballs
+/// nest every file under a `<name>-<version>/` prefix, which we ignore).
+pub async fn read_crate_file(
+    client: &Client,
+    name: &str,
+    version: &str,
+    file_name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let bytes = download_crate_tarball(client, name, version).await?;
+    let decoder = GzDecoder::new(&bytes[..]);
+    let mut archive = Archive::new(decoder);
+
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Failed to read tarball entries for {name} {version}"))?
+    {
+        let mut entry = entry.with_context(|| format!("Failed to read a tarball entry for {name} {version}"))?;
+        let path = entry.path()?.into_owned();
+        if path.file_name().and_then(|f| f.to_str()) == Some(file_name) {
+            let mut buf = Vec::new();
+            entry.read_to_end(&mut buf)?;
+            return Ok(Some(buf));
+        }
+    }
+
+    Ok(None)
+}